@@ -1,5 +1,6 @@
 import * as anchor from "@coral-xyz/anchor";
 import { PublicKey } from "@solana/web3.js";
+import { BN } from "bn.js";
 import {
   getAssociatedTokenAddress,
   getOrCreateAssociatedTokenAccount,
@@ -16,14 +17,15 @@ import {
   const mint = new PublicKey(process.env.ABC_MINT!);
 
   // Derive pool PDAs
+  const poolIdSeed = (id: number) => new BN(id).toArrayLike(Buffer, "le", 8);
   const [poolA] = PublicKey.findProgramAddressSync(
-    [Buffer.from("pool"), mint.toBuffer(), payer.publicKey.toBuffer()],
+    [Buffer.from("pool"), mint.toBuffer(), payer.publicKey.toBuffer(), poolIdSeed(0)],
     program.programId
   );
   const poolASigner = poolA; // same seeds
 
   const [poolB] = PublicKey.findProgramAddressSync(
-    [Buffer.from("pool"), mint.toBuffer(), payer.publicKey.toBuffer()],
+    [Buffer.from("pool"), mint.toBuffer(), payer.publicKey.toBuffer(), poolIdSeed(1)],
     program.programId
   );
   const poolBSigner = poolB;
@@ -44,15 +46,51 @@ import {
     true
   );
 
+  // Reward vaults are owned by a separate PDA so payouts never touch principal
+  const [rewardASigner] = PublicKey.findProgramAddressSync(
+    [Buffer.from("reward"), poolA.toBuffer()],
+    program.programId
+  );
+  const [rewardBSigner] = PublicKey.findProgramAddressSync(
+    [Buffer.from("reward"), poolB.toBuffer()],
+    program.programId
+  );
+  const rewardVaultA = await getOrCreateAssociatedTokenAccount(
+    connection,
+    payer,
+    mint,
+    rewardASigner,
+    true
+  );
+  const rewardVaultB = await getOrCreateAssociatedTokenAccount(
+    connection,
+    payer,
+    mint,
+    rewardBSigner,
+    true
+  );
+
+  // Program-wide stats singleton; also numbers pools
+  const [stats] = PublicKey.findProgramAddressSync(
+    [Buffer.from("protocol_stats")],
+    program.programId
+  );
+
+  // No early-exit penalty curve: unstake inside the lockup fails
+  const NO_PENALTY = { linear: false, pointsBps: [0, 0, 0, 0] };
+
   // Initialize Pool A: 10% APY, 0 lockup
   await program.methods
-    .initializePool(1000, 0)
+    .initializePool(new BN(0), 1000, 0, false, NO_PENALTY, new BN(0))
     .accounts({
       admin: payer.publicKey,
       pool: poolA,
       poolSigner: poolASigner,
       mint,
       vaultAta: vaultA.address,
+      rewardSigner: rewardASigner,
+      rewardVaultAta: rewardVaultA.address,
+      stats,
       tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
       associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
@@ -64,13 +102,16 @@ import {
   // Initialize Pool B: 20% APY, 30d lockup
   const lockup = 30 * 24 * 3600;
   await program.methods
-    .initializePool(2000, lockup)
+    .initializePool(new BN(1), 2000, lockup, false, NO_PENALTY, new BN(0))
     .accounts({
       admin: payer.publicKey,
       pool: poolB,
       poolSigner: poolBSigner,
       mint,
       vaultAta: vaultB.address,
+      rewardSigner: rewardBSigner,
+      rewardVaultAta: rewardVaultB.address,
+      stats,
       tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
       associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
@@ -79,6 +120,6 @@ import {
     })
     .rpc();
 
-  console.log("Pool A:", poolA.toBase58(), "Vault:", vaultA.address.toBase58());
-  console.log("Pool B:", poolB.toBase58(), "Vault:", vaultB.address.toBase58());
+  console.log("Pool A:", poolA.toBase58(), "Vault:", vaultA.address.toBase58(), "Reward vault:", rewardVaultA.address.toBase58());
+  console.log("Pool B:", poolB.toBase58(), "Vault:", vaultB.address.toBase58(), "Reward vault:", rewardVaultB.address.toBase58());
 })();
@@ -58,7 +58,7 @@ async function main() {
 
   // --- Derive Pool PDA ---
   const [poolPda] = PublicKey.findProgramAddressSync(
-    [Buffer.from("pool"), mint.toBuffer()],
+    [Buffer.from("pool"), mint.toBuffer(), wallet.publicKey.toBuffer(), new anchor.BN(0).toArrayLike(Buffer, "le", 8)],
     program.programId
   );
 
@@ -71,7 +71,7 @@ async function main() {
   // --- Stake ---
   console.log(`\nStaking ${STAKE_AMOUNT} ABC...`);
   await program.methods
-    .stake(stakeAmountUi)
+    .stake(stakeAmountUi, null, null, 0, null)
     .accounts({
       user: wallet.publicKey,
       // userAta is not a valid account for this instruction, so we remove it
@@ -6,17 +6,26 @@ import {
   getAssociatedTokenAddress,
   createMint,
   mintTo,
+  transfer,
 } from "@solana/spl-token";
-import { Keypair, PublicKey } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey } from "@solana/web3.js";
+import * as fs from "fs";
+import * as path from "path";
 
 const DECIMALS = 9;
 const ONE = BigInt("1000000000");
 const SECONDS_PER_YEAR = 31_536_000;
+// Early exit disabled: unstake inside the lockup fails
+const NO_PENALTY = { linear: false, pointsBps: [0, 0, 0, 0] };
 
 function toBase(n: number): bigint {
   return BigInt(Math.floor(n * Math.pow(10, DECIMALS)));
 }
 
+function poolIdSeed(id: number): Buffer {
+  return new BN(id).toArrayLike(Buffer, "le", 8);
+}
+
 function fromBase(x: bigint): number {
   return Number(x) / 1e9;
 }
@@ -37,13 +46,26 @@ describe("abc_staking", () => {
   let poolA: PublicKey;
   let poolASigner: PublicKey;
   let vaultA: PublicKey;
+  let rewardASigner: PublicKey;
+  let rewardVaultA: PublicKey;
   let userStakeA: PublicKey;
 
   let poolB: PublicKey;
   let poolBSigner: PublicKey;
   let vaultB: PublicKey;
+  let rewardBSigner: PublicKey;
+  let rewardVaultB: PublicKey;
   let userStakeB: PublicKey;
 
+  let stats: PublicKey;
+
+  // An owner's total across their positions in a pool
+  const ownerStakeOf = (pool: PublicKey, owner: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("owner_stake"), pool.toBuffer(), owner.toBuffer()],
+      program.programId
+    )[0];
+
   it("A) Setup & Initialization", async () => {
     // Create ABC mint 9 decimals and user ATA, mint 1,000 ABC
     mint = await createMint(provider.connection, user, user.publicKey, null, 9);
@@ -60,12 +82,12 @@ describe("abc_staking", () => {
 
     // Derive pool PDA and vaults
     [poolA] = PublicKey.findProgramAddressSync(
-      [Buffer.from("pool"), mint.toBuffer(), user.publicKey.toBuffer()],
+      [Buffer.from("pool"), mint.toBuffer(), user.publicKey.toBuffer(), poolIdSeed(0)],
       program.programId
     );
     poolASigner = poolA;
     [poolB] = PublicKey.findProgramAddressSync(
-      [Buffer.from("pool"), mint.toBuffer(), user.publicKey.toBuffer()],
+      [Buffer.from("pool"), mint.toBuffer(), user.publicKey.toBuffer(), poolIdSeed(1)],
       program.programId
     );
     poolBSigner = poolB;
@@ -88,15 +110,40 @@ describe("abc_staking", () => {
     );
     vaultB = vaultBAcc.address;
 
+    // Reward vaults live under a separate signer from the stake vaults
+    [rewardASigner] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reward"), poolA.toBuffer()],
+      program.programId
+    );
+    [rewardBSigner] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reward"), poolB.toBuffer()],
+      program.programId
+    );
+    rewardVaultA = (await getOrCreateAssociatedTokenAccount(
+      provider.connection, user, mint, rewardASigner, true
+    )).address;
+    rewardVaultB = (await getOrCreateAssociatedTokenAccount(
+      provider.connection, user, mint, rewardBSigner, true
+    )).address;
+
+    // Program-wide stats singleton; also numbers pools
+    [stats] = PublicKey.findProgramAddressSync(
+      [Buffer.from("protocol_stats")],
+      program.programId
+    );
+
     // Init pools
     await program.methods
-      .initializePool(1000, 0)
+      .initializePool(new BN(0), 1000, 0, false, NO_PENALTY, new BN(0))
       .accounts({
         admin: user.publicKey,
         pool: poolA,
         poolSigner: poolASigner,
         mint,
         vaultAta: vaultA,
+        rewardSigner: rewardASigner,
+        rewardVaultAta: rewardVaultA,
+        stats,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
@@ -106,13 +153,16 @@ describe("abc_staking", () => {
       .rpc();
 
     await program.methods
-      .initializePool(2000, 30 * 24 * 3600)
+      .initializePool(new BN(1), 2000, 30 * 24 * 3600, false, NO_PENALTY, new BN(0))
       .accounts({
         admin: user.publicKey,
         pool: poolB,
         poolSigner: poolBSigner,
         mint,
         vaultAta: vaultB,
+        rewardSigner: rewardBSigner,
+        rewardVaultAta: rewardVaultB,
+        stats,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
@@ -121,6 +171,20 @@ describe("abc_staking", () => {
       })
       .rpc();
 
+    // Fund rewards through the program so reward_reserve tracks the backing
+    for (const [pool, rewardVault] of [[poolA, rewardVaultA], [poolB, rewardVaultB]]) {
+      await program.methods
+        .fundRewards(new BN(toBase(100).toString()))
+        .accounts({
+          funder: user.publicKey,
+          funderAta: userAta,
+          pool,
+          rewardVaultAta: rewardVault,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+    }
+
     // Basic asserts: PDAs exist
     // NOTE: The correct way to fetch pool accounts depends on your IDL and Anchor codegen.
     // If 'pool' is not a valid account namespace, you may need to use the correct account name.
@@ -134,6 +198,8 @@ describe("abc_staking", () => {
 
     expect(poolAAcc.mint.toBase58()).to.eq(mint.toBase58());
     expect(poolAAcc.vault.toBase58()).to.eq(vaultA.toBase58());
+    expect(poolAAcc.rewardVault.toBase58()).to.eq(rewardVaultA.toBase58());
+    expect(poolAAcc.rewardReserve.toString()).to.eq(toBase(100).toString());
     expect(poolBAcc.lockupSeconds).to.eq(30 * 24 * 3600);
   });
 
@@ -144,15 +210,17 @@ describe("abc_staking", () => {
     );
 
     await program.methods
-      .stake(new BN(toBase(10)))
+      .stake(new BN(toBase(10)), null, null, 0, null)
       .accounts({
         user: user.publicKey,
         userStake: userStakeA,
         userAta,
         pool: poolA,
+        ownerStake: ownerStakeOf(poolA, user.publicKey),
         poolSigner: poolASigner,
         vaultAta: vaultA,
         mint,
+        stats,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -165,6 +233,12 @@ describe("abc_staking", () => {
       .accounts({ admin: user.publicKey, pool: poolA })
       .rpc();
 
+    // The read-only view should agree with what claim then pays out
+    const pending = await program.methods
+      .pendingRewards()
+      .accounts({ pool: poolA, userStake: userStakeA })
+      .view();
+
     // Claim
     const before = (await provider.connection.getTokenAccountBalance(userAta)).value.uiAmount!;
     await program.methods
@@ -174,8 +248,9 @@ describe("abc_staking", () => {
         userStake: userStakeA,
         userAta,
         pool: poolA,
-        poolSigner: poolASigner,
-        vaultAta: vaultA,
+        rewardSigner: rewardASigner,
+        rewardVaultAta: rewardVaultA,
+        stats,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -186,6 +261,15 @@ describe("abc_staking", () => {
 
     const expected = rewardLinear(1000, 10, 30 * 24 * 3600);
     expect(Math.abs(claimed - expected)).to.be.lessThan(0.0001);
+    expect(Math.abs(claimed - fromBase(BigInt(pending.amount.toString())))).to.be.lessThan(0.0001);
+
+    // Vaults still back every obligation after the payout
+    const liabilities = await program.methods
+      .poolLiabilities()
+      .accounts({ pool: poolA, vaultAta: vaultA, rewardVaultAta: rewardVaultA })
+      .view();
+    expect(liabilities.principal.toString()).to.eq(toBase(10).toString());
+    expect(liabilities.solvent).to.eq(true);
   });
 
   it("C) Stake/Accrue/Claim/Unstake (20% APY, lockup)", async () => {
@@ -195,15 +279,17 @@ describe("abc_staking", () => {
     );
 
     await program.methods
-      .stake(new BN(toBase(10)))
+      .stake(new BN(toBase(10)), null, null, 0, null)
       .accounts({
         user: user.publicKey,
         userStake: userStakeB,
         userAta,
         pool: poolB,
+        ownerStake: ownerStakeOf(poolB, user.publicKey),
         poolSigner: poolBSigner,
         vaultAta: vaultB,
         mint,
+        stats,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -220,8 +306,10 @@ describe("abc_staking", () => {
           userStake: userStakeB,
           userAta,
           pool: poolB,
+          ownerStake: ownerStakeOf(poolB, user.publicKey),
           poolSigner: poolBSigner,
           vaultAta: vaultB,
+          stats,
           tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,
           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -247,8 +335,9 @@ describe("abc_staking", () => {
         userStake: userStakeB,
         userAta,
         pool: poolB,
-        poolSigner: poolBSigner,
-        vaultAta: vaultB,
+        rewardSigner: rewardBSigner,
+        rewardVaultAta: rewardVaultB,
+        stats,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -266,8 +355,10 @@ describe("abc_staking", () => {
         userStake: userStakeB,
         userAta,
         pool: poolB,
+        ownerStake: ownerStakeOf(poolB, user.publicKey),
         poolSigner: poolBSigner,
         vaultAta: vaultB,
+        stats,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -296,7 +387,7 @@ describe("abc_staking", () => {
 
     // t0: User A stakes 10
     await program.methods
-      .stake(new BN(toBase(10)))
+      .stake(new BN(toBase(10)), null, null, 0, null)
       .accounts({
         user: user.publicKey,
         userStake: (await PublicKey.findProgramAddress(
@@ -305,9 +396,11 @@ describe("abc_staking", () => {
         ).then(r => r)),
         userAta,
         pool: poolA,
+        ownerStake: ownerStakeOf(poolA, user.publicKey),
         poolSigner: poolASigner,
         vaultAta: vaultA,
         mint,
+        stats,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -317,15 +410,17 @@ describe("abc_staking", () => {
     // After 10 days: User B stakes 10
     await program.methods.setTimeOffset(new BN(10 * 24 * 3600)).accounts({ admin: user.publicKey, pool: poolA }).rpc();
     await program.methods
-      .stake(new BN(toBase(10)))
+      .stake(new BN(toBase(10)), null, null, 0, null)
       .accounts({
         user: other.publicKey,
         userStake: userStakeA_other,
         userAta: otherAtaAcc.address,
         pool: poolA,
+        ownerStake: ownerStakeOf(poolA, other.publicKey),
         poolSigner: poolASigner,
         vaultAta: vaultA,
         mint,
+        stats,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -347,8 +442,9 @@ describe("abc_staking", () => {
         ).then(r => r)),
         userAta,
         pool: poolA,
-        poolSigner: poolASigner,
-        vaultAta: vaultA,
+        rewardSigner: rewardASigner,
+        rewardVaultAta: rewardVaultA,
+        stats,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -365,8 +461,9 @@ describe("abc_staking", () => {
         userStake: userStakeA_other,
         userAta: otherAtaAcc.address,
         pool: poolA,
-        poolSigner: poolASigner,
-        vaultAta: vaultA,
+        rewardSigner: rewardASigner,
+        rewardVaultAta: rewardVaultA,
+        stats,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -387,7 +484,7 @@ describe("abc_staking", () => {
     let failed = false;
     try {
       await program.methods
-        .stake(new BN(0))
+        .stake(new BN(0), null, null, 0, null)
         .accounts({
           user: user.publicKey,
           userStake: (await PublicKey.findProgramAddress(
@@ -396,19 +493,77 @@ describe("abc_staking", () => {
           ).then(r => r)),
           userAta,
           pool: poolA,
+          ownerStake: ownerStakeOf(poolA, user.publicKey),
+          poolSigner: poolASigner,
+          vaultAta: vaultA,
+          mint,
+          stats,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        })
+        .rpc();
+    } catch (e) {
+      failed = true;
+    }
+    expect(failed).to.eq(true);
+
+    // Retried stake with a used nonce -> error, no second deposit
+    const nonceStake = () =>
+      program.methods
+        .stake(new BN(toBase(1)), new BN(1), null, 0, null)
+        .accounts({
+          user: user.publicKey,
+          userStake: userStakeA,
+          userAta,
+          pool: poolA,
+          ownerStake: ownerStakeOf(poolA, user.publicKey),
           poolSigner: poolASigner,
           vaultAta: vaultA,
           mint,
+          stats,
           tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,
           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
         })
         .rpc();
+    await nonceStake();
+    failed = false;
+    try {
+      await nonceStake();
     } catch (e) {
       failed = true;
     }
     expect(failed).to.eq(true);
 
+    // A second position is independent of the original one
+    const [userStakeA1] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_stake"), poolA.toBuffer(), user.publicKey.toBuffer(), new BN(1).toArrayLike(Buffer, "le", 4)],
+      program.programId
+    );
+    const originalBefore = (await program.account.userStake.fetch(userStakeA)).amountStaked;
+    await program.methods
+      .stake(new BN(toBase(2)), null, null, 1, null)
+      .accounts({
+        user: user.publicKey,
+        userStake: userStakeA1,
+        userAta,
+        pool: poolA,
+        ownerStake: ownerStakeOf(poolA, user.publicKey),
+        poolSigner: poolASigner,
+        vaultAta: vaultA,
+        mint,
+        stats,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .rpc();
+    const second = await program.account.userStake.fetch(userStakeA1);
+    expect(second.positionIndex).to.eq(1);
+    expect(second.amountStaked.toString()).to.eq(toBase(2).toString());
+    expect((await program.account.userStake.fetch(userStakeA)).amountStaked.toString()).to.eq(originalBefore.toString());
+
     // Claim with zero stake -> should not crash
     // Use a new user with no stake
     const temp = Keypair.generate();
@@ -430,8 +585,9 @@ describe("abc_staking", () => {
         userStake: tempStake,
         userAta: tempAta.address,
         pool: poolA,
-        poolSigner: poolASigner,
-        vaultAta: vaultA,
+        rewardSigner: rewardASigner,
+        rewardVaultAta: rewardVaultA,
+        stats,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -441,4 +597,707 @@ describe("abc_staking", () => {
 
     // total_staked == 0 safe path covered implicitly when no one has staked
   });
+
+  it("F) Reward math matches canonical vectors", async () => {
+    // Generated by the test_vectors binary; see test_vectors.rs
+    const vectors = JSON.parse(fs.readFileSync(path.join(__dirname, "..", "test_vectors.json"), "utf8"));
+    const FP = BigInt(1) << BigInt(64);
+
+    for (const v of vectors.linear) {
+      const rate = (BigInt(v.apy_bps) * FP) / BigInt(10_000) / BigInt(SECONDS_PER_YEAR);
+      const acc = BigInt(v.seconds) * rate;
+      const owed = BigInt(v.principal) * acc;
+      expect(rate.toString()).to.eq(v.rate_fp);
+      expect(acc.toString()).to.eq(v.acc_fp);
+      expect(owed.toString()).to.eq(v.owed_fp);
+      expect((owed / FP).toString()).to.eq(String(v.tokens));
+    }
+
+    for (const v of vectors.fixed_emission) {
+      const acc = (BigInt(v.seconds) * (BigInt(v.emission_per_second) * FP)) / BigInt(v.total_weight);
+      const owed = BigInt(v.weight) * acc;
+      expect(acc.toString()).to.eq(v.acc_fp);
+      expect(owed.toString()).to.eq(v.owed_fp);
+      expect((owed / FP).toString()).to.eq(String(v.tokens));
+    }
+
+    // Same fixed-point steps as apy_to_compound_rate_fp, ln_fp and pow_fp
+    const mulFp = (a: bigint, b: bigint) => (a * b) >> BigInt(64);
+    const lnFp = (y: bigint) => {
+      const z = ((y - FP) * FP) / (y + FP);
+      const z2 = mulFp(z, z);
+      let term = z;
+      let sum = z;
+      for (let k = BigInt(1); term > BigInt(0); k++) {
+        term = mulFp(term, z2);
+        sum += term / (BigInt(2) * k + BigInt(1));
+      }
+      return BigInt(2) * sum;
+    };
+    const powFp = (base: bigint, exp: bigint) => {
+      let result = FP;
+      let b = base;
+      while (exp > BigInt(0)) {
+        if (exp & BigInt(1)) result = mulFp(result, b);
+        exp >>= BigInt(1);
+        if (exp > BigInt(0)) b = mulFp(b, b);
+      }
+      return result;
+    };
+    for (const v of vectors.compounding) {
+      const x = lnFp(FP + (BigInt(v.apy_bps) * FP) / BigInt(10_000)) / BigInt(SECONDS_PER_YEAR);
+      const rate = x + mulFp(x, x) / BigInt(2);
+      const index = powFp(FP + rate, BigInt(v.seconds));
+      const balance = BigInt(v.principal) * FP;
+      const owed = (balance * index) / FP - balance;
+      expect(rate.toString()).to.eq(v.rate_fp);
+      expect(index.toString()).to.eq(v.index_fp);
+      expect(owed.toString()).to.eq(v.owed_fp);
+      expect((owed / FP).toString()).to.eq(String(v.tokens));
+    }
+  });
+
+  it("G) Co-signer threshold covers an exit split into chunks", async () => {
+    const cosigner = Keypair.generate();
+    await program.methods
+      .setCosignerPolicy(cosigner.publicKey, new BN(toBase(1).toString()))
+      .accounts({ user: user.publicKey, pool: poolA, userStake: userStakeA })
+      .rpc();
+
+    const unstakeChunk = (extra = {}) =>
+      program.methods
+        .unstake(new BN(toBase(0.6).toString()))
+        .accounts({
+          user: user.publicKey,
+          userStake: userStakeA,
+          userAta,
+          pool: poolA,
+          ownerStake: ownerStakeOf(poolA, user.publicKey),
+          poolSigner: poolASigner,
+          vaultAta: vaultA,
+          stats,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          ...extra,
+        });
+
+    // Each chunk is under the threshold, but the second takes the day's total over it
+    await unstakeChunk().rpc();
+    let failed = false;
+    try {
+      await unstakeChunk().rpc();
+    } catch (e) {
+      failed = true;
+    }
+    expect(failed).to.eq(true);
+
+    // The co-signer can still approve it
+    await unstakeChunk({ cosigner: cosigner.publicKey }).signers([cosigner]).rpc();
+
+    await program.methods
+      .setCosignerPolicy(PublicKey.default, new BN(0))
+      .accounts({ user: user.publicKey, currentCosigner: cosigner.publicKey, pool: poolA, userStake: userStakeA })
+      .signers([cosigner])
+      .rpc();
+  });
+
+  it("H) Cloned pools carry the source's parameters", async () => {
+    const asAdmin = (m: any) => m.accounts({ admin: user.publicKey, pool: poolB }).rpc();
+    await asAdmin(program.methods.setClaimFee(250));
+    await asAdmin(program.methods.setReferralBps(100));
+    await asAdmin(program.methods.setPenaltyRedistribution(true));
+    await asAdmin(program.methods.setCooldown(7 * 24 * 3600));
+    await asAdmin(program.methods.setEmergencyPenalty(1500));
+    await asAdmin(program.methods.setMaxStakePerUser(new BN(toBase(500).toString())));
+    await asAdmin(program.methods.setMinStakeAmount(new BN(toBase(0.5).toString())));
+    await asAdmin(program.methods.setMaxTotalStaked(new BN(toBase(10_000).toString())));
+    await asAdmin(program.methods.setClaimCooldown(3600));
+
+    const [poolC] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool"), mint.toBuffer(), user.publicKey.toBuffer(), poolIdSeed(2)],
+      program.programId
+    );
+    const [rewardCSigner] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reward"), poolC.toBuffer()],
+      program.programId
+    );
+    const vaultC = (await getOrCreateAssociatedTokenAccount(
+      provider.connection, user, mint, poolC, true
+    )).address;
+    const rewardVaultC = (await getOrCreateAssociatedTokenAccount(
+      provider.connection, user, mint, rewardCSigner, true
+    )).address;
+
+    await program.methods
+      .clonePool(new BN(2))
+      .accounts({
+        admin: user.publicKey,
+        sourcePool: poolB,
+        pool: poolC,
+        poolSigner: poolC,
+        mint,
+        vaultAta: vaultC,
+        rewardSigner: rewardCSigner,
+        rewardVaultAta: rewardVaultC,
+        stats,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const clone = await program.account.pool.fetch(poolC);
+    expect(clone.lockupSeconds).to.eq(30 * 24 * 3600);
+    expect(clone.claimFeeBps).to.eq(250);
+    expect(clone.referralBps).to.eq(100);
+    expect(clone.redistributePenalties).to.eq(true);
+    expect(clone.cooldownSeconds).to.eq(7 * 24 * 3600);
+    expect(clone.emergencyPenaltyBps).to.eq(1500);
+    expect(clone.maxStakePerUser.toString()).to.eq(toBase(500).toString());
+    expect(clone.minStakeAmount.toString()).to.eq(toBase(0.5).toString());
+    expect(clone.maxTotalStaked.toString()).to.eq(toBase(10_000).toString());
+    expect(clone.claimCooldownSeconds).to.eq(3600);
+  });
+
+  it("I) A hold placed while unbonding blocks the withdrawal", async () => {
+    const asAdmin = (m: any) => m.accounts({ admin: user.publicKey, pool: poolA }).rpc();
+    const position = { admin: user.publicKey, pool: poolA, userStake: userStakeA };
+    await asAdmin(program.methods.setCooldown(3600));
+
+    await program.methods
+      .requestUnstake(new BN(toBase(0.5).toString()))
+      .accounts({
+        user: user.publicKey,
+        pool: poolA,
+        userStake: userStakeA,
+        ownerStake: ownerStakeOf(poolA, user.publicKey),
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods.freezePosition({ accrue: {} }).accounts(position).rpc();
+
+    const offset = (await program.account.pool.fetch(poolA)).timeOffset;
+    await asAdmin(program.methods.setTimeOffset(offset.add(new BN(3601))));
+
+    const withdraw = () =>
+      program.methods
+        .withdrawUnstaked()
+        .accounts({
+          user: user.publicKey,
+          pool: poolA,
+          userStake: userStakeA,
+          userAta,
+          poolSigner: poolASigner,
+          vaultAta: vaultA,
+          stats,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+    // Cooldown is over, but the hold still applies
+    let failed = false;
+    try {
+      await withdraw();
+    } catch (e) {
+      failed = true;
+    }
+    expect(failed).to.eq(true);
+
+    await program.methods.unfreezePosition().accounts(position).rpc();
+    const before = (await provider.connection.getTokenAccountBalance(userAta)).value.amount;
+    await withdraw();
+    const after = (await provider.connection.getTokenAccountBalance(userAta)).value.amount;
+    expect((BigInt(after) - BigInt(before)).toString()).to.eq(toBase(0.5).toString());
+
+    await asAdmin(program.methods.setCooldown(0));
+  });
+
+  it("J) A receipted position exits fully through the cooldown", async () => {
+    const asAdmin = (m: any) => m.accounts({ admin: user.publicKey, pool: poolA }).rpc();
+    await asAdmin(program.methods.setCooldown(3600));
+
+    const [userStakeA2] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_stake"), poolA.toBuffer(), user.publicKey.toBuffer(), new BN(2).toArrayLike(Buffer, "le", 4)],
+      program.programId
+    );
+    await program.methods
+      .stake(new BN(toBase(2)), null, null, 2, null)
+      .accounts({
+        user: user.publicKey,
+        userStake: userStakeA2,
+        userAta,
+        pool: poolA,
+        ownerStake: ownerStakeOf(poolA, user.publicKey),
+        poolSigner: poolASigner,
+        vaultAta: vaultA,
+        mint,
+        stats,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .rpc();
+
+    // Mint the receipt and hand it to someone else
+    const [receiptMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), userStakeA2.toBuffer()],
+      program.programId
+    );
+    const ownerReceipt = await getAssociatedTokenAddress(receiptMint, user.publicKey);
+    await program.methods
+      .mintPositionReceipt()
+      .accounts({
+        user: user.publicKey,
+        pool: poolA,
+        userStake: userStakeA2,
+        poolSigner: poolASigner,
+        receiptMint,
+        receiptAta: ownerReceipt,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    const holder = Keypair.generate();
+    const holderReceipt = (await getOrCreateAssociatedTokenAccount(
+      provider.connection, user, receiptMint, holder.publicKey
+    )).address;
+    const holderAta = (await getOrCreateAssociatedTokenAccount(
+      provider.connection, user, mint, holder.publicKey
+    )).address;
+    await transfer(provider.connection, user, ownerReceipt, holderReceipt, user, 1);
+
+    // The full request burns the receipt
+    await program.methods
+      .requestUnstake(new BN(toBase(2).toString()))
+      .accounts({
+        user: holder.publicKey,
+        pool: poolA,
+        userStake: userStakeA2,
+        ownerStake: ownerStakeOf(poolA, user.publicKey),
+        receiptAta: holderReceipt,
+        receiptMint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([holder])
+      .rpc();
+    const requested = await program.account.userStake.fetch(userStakeA2);
+    expect(requested.receiptMint.toBase58()).to.eq(PublicKey.default.toBase58());
+    expect(requested.unbondingOwner.toBase58()).to.eq(holder.publicKey.toBase58());
+
+    const offset = (await program.account.pool.fetch(poolA)).timeOffset;
+    await asAdmin(program.methods.setTimeOffset(offset.add(new BN(3601))));
+
+    const withdraw = (signer: Keypair, to: PublicKey) =>
+      program.methods
+        .withdrawUnstaked()
+        .accounts({
+          user: signer.publicKey,
+          pool: poolA,
+          userStake: userStakeA2,
+          userAta: to,
+          poolSigner: poolASigner,
+          vaultAta: vaultA,
+          stats,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([signer])
+        .rpc();
+
+    // Unbonding belongs to the holder, not the position's owner
+    let failed = false;
+    try {
+      await withdraw(user, userAta);
+    } catch (e) {
+      failed = true;
+    }
+    expect(failed).to.eq(true);
+
+    await withdraw(holder, holderAta);
+    const paid = (await provider.connection.getTokenAccountBalance(holderAta)).value.amount;
+    expect(paid).to.eq(toBase(2).toString());
+    expect((await program.account.userStake.fetch(userStakeA2)).unbondingAmount.toString()).to.eq("0");
+
+    await asAdmin(program.methods.setCooldown(0));
+  });
+
+  it("K) The per-user cap covers all of an owner's positions", async () => {
+    const asAdmin = (m: any) => m.accounts({ admin: user.publicKey, pool: poolA }).rpc();
+    const ownerStake = ownerStakeOf(poolA, user.publicKey);
+    const held = (await program.account.ownerStake.fetch(ownerStake)).amountStaked;
+    await asAdmin(program.methods.setMaxStakePerUser(held.add(new BN(toBase(1).toString()))));
+
+    // A fresh position is under the cap on its own, but not with the owner's others
+    const [userStakeA3] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_stake"), poolA.toBuffer(), user.publicKey.toBuffer(), new BN(3).toArrayLike(Buffer, "le", 4)],
+      program.programId
+    );
+    let code = "";
+    try {
+      await program.methods
+        .stake(new BN(toBase(2).toString()), null, null, 3, null)
+        .accounts({
+          user: user.publicKey,
+          userStake: userStakeA3,
+          userAta,
+          pool: poolA,
+          ownerStake,
+          poolSigner: poolASigner,
+          vaultAta: vaultA,
+          mint,
+          stats,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        })
+        .rpc();
+    } catch (e) {
+      code = e.error?.errorCode?.code;
+    }
+    expect(code).to.eq("StakeCapExceeded");
+    expect((await program.account.ownerStake.fetch(ownerStake)).amountStaked.toString()).to.eq(held.toString());
+
+    await asAdmin(program.methods.setMaxStakePerUser(new BN(0)));
+  });
+
+  it("L) Compounding into a full pool fails", async () => {
+    const asAdmin = (m: any) => m.accounts({ admin: user.publicKey, pool: poolA }).rpc();
+    const totalStaked = (await program.account.pool.fetch(poolA)).totalStaked;
+    await asAdmin(program.methods.setMaxTotalStaked(totalStaked));
+
+    let code = "";
+    try {
+      await program.methods
+        .compound()
+        .accounts({
+          user: user.publicKey,
+          userStake: userStakeA,
+          ownerStake: ownerStakeOf(poolA, user.publicKey),
+          pool: poolA,
+          poolSigner: poolASigner,
+          vaultAta: vaultA,
+          rewardSigner: rewardASigner,
+          rewardVaultAta: rewardVaultA,
+          stats,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    } catch (e) {
+      code = e.error?.errorCode?.code;
+    }
+    expect(code).to.eq("PoolFull");
+    expect((await program.account.pool.fetch(poolA)).totalStaked.toString()).to.eq(totalStaked.toString());
+
+    await asAdmin(program.methods.setMaxTotalStaked(new BN(0)));
+  });
+
+  it("M) A second claim inside the cooldown is rejected", async () => {
+    const asAdmin = (m: any) => m.accounts({ admin: user.publicKey, pool: poolA }).rpc();
+    await asAdmin(program.methods.setClaimCooldown(3600));
+    const offset = (await program.account.pool.fetch(poolA)).timeOffset;
+    await asAdmin(program.methods.setTimeOffset(offset.add(new BN(3601))));
+
+    const claim = () =>
+      program.methods
+        .claim()
+        .accounts({
+          user: user.publicKey,
+          userStake: userStakeA,
+          userAta,
+          pool: poolA,
+          rewardSigner: rewardASigner,
+          rewardVaultAta: rewardVaultA,
+          stats,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        })
+        .rpc();
+
+    const before = (await provider.connection.getTokenAccountBalance(userAta)).value.amount;
+    await claim();
+    const after = (await provider.connection.getTokenAccountBalance(userAta)).value.amount;
+    expect(BigInt(after) > BigInt(before)).to.eq(true);
+
+    let code = "";
+    try {
+      await claim();
+    } catch (e) {
+      code = e.error?.errorCode?.code;
+    }
+    expect(code).to.eq("ClaimCooldown");
+
+    await asAdmin(program.methods.setClaimCooldown(0));
+  });
+
+  it("N) Compounding counts as owner activity against the beneficiary", async () => {
+    const asAdmin = (m: any) => m.accounts({ admin: user.publicKey, pool: poolA }).rpc();
+    const advance = async (seconds: number) => {
+      const offset = (await program.account.pool.fetch(poolA)).timeOffset;
+      await asAdmin(program.methods.setTimeOffset(offset.add(new BN(seconds))));
+    };
+    const inactivity = 30 * 24 * 3600;
+    const beneficiary = Keypair.generate();
+    const beneficiaryAta = (await getOrCreateAssociatedTokenAccount(
+      provider.connection, user, mint, beneficiary.publicKey
+    )).address;
+    await program.methods
+      .setBeneficiary(beneficiary.publicKey, inactivity)
+      .accounts({ user: user.publicKey, pool: poolA, userStake: userStakeA })
+      .rpc();
+
+    // The owner compounds shortly before the beneficiary's window would open
+    await advance(inactivity - 3600);
+    await program.methods
+      .compound()
+      .accounts({
+        user: user.publicKey,
+        userStake: userStakeA,
+        ownerStake: ownerStakeOf(poolA, user.publicKey),
+        pool: poolA,
+        poolSigner: poolASigner,
+        vaultAta: vaultA,
+        rewardSigner: rewardASigner,
+        rewardVaultAta: rewardVaultA,
+        stats,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await advance(2 * 3600);
+
+    let code = "";
+    try {
+      await program.methods
+        .claim()
+        .accounts({
+          user: beneficiary.publicKey,
+          userStake: userStakeA,
+          userAta: beneficiaryAta,
+          pool: poolA,
+          rewardSigner: rewardASigner,
+          rewardVaultAta: rewardVaultA,
+          stats,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        })
+        .signers([beneficiary])
+        .rpc();
+    } catch (e) {
+      code = e.error?.errorCode?.code;
+    }
+    expect(code).to.eq("OwnerStillActive");
+
+    await program.methods
+      .setBeneficiary(PublicKey.default, 0)
+      .accounts({ user: user.publicKey, pool: poolA, userStake: userStakeA })
+      .rpc();
+  });
+
+  it("O) Staking below the pool minimum fails", async () => {
+    const asAdmin = (m: any) => m.accounts({ admin: user.publicKey, pool: poolA }).rpc();
+    await asAdmin(program.methods.setMinStakeAmount(new BN(toBase(5).toString())));
+
+    const [userStakeA4] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_stake"), poolA.toBuffer(), user.publicKey.toBuffer(), new BN(4).toArrayLike(Buffer, "le", 4)],
+      program.programId
+    );
+    let code = "";
+    try {
+      await program.methods
+        .stake(new BN(toBase(1).toString()), null, null, 4, null)
+        .accounts({
+          user: user.publicKey,
+          userStake: userStakeA4,
+          userAta,
+          pool: poolA,
+          ownerStake: ownerStakeOf(poolA, user.publicKey),
+          poolSigner: poolASigner,
+          vaultAta: vaultA,
+          mint,
+          stats,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        })
+        .rpc();
+    } catch (e) {
+      code = e.error?.errorCode?.code;
+    }
+    expect(code).to.eq("BelowMinStake");
+
+    await asAdmin(program.methods.setMinStakeAmount(new BN(0)));
+  });
+
+  it("P) A split-off keeps the lock and merges back whole", async () => {
+    const positionOf = (index: number) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("user_stake"), poolA.toBuffer(), user.publicKey.toBuffer(), new BN(index).toArrayLike(Buffer, "le", 4)],
+        program.programId
+      )[0];
+    const original = positionOf(6);
+    const splitOff = positionOf(7);
+    await program.methods
+      .stake(new BN(toBase(10).toString()), null, null, 6, null)
+      .accounts({
+        user: user.publicKey,
+        userStake: original,
+        userAta,
+        pool: poolA,
+        ownerStake: ownerStakeOf(poolA, user.publicKey),
+        poolSigner: poolASigner,
+        vaultAta: vaultA,
+        mint,
+        stats,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .rpc();
+    const offset = (await program.account.pool.fetch(poolA)).timeOffset.toNumber();
+    const unlockTs = Math.floor(Date.now() / 1000) + offset + 10 * 24 * 3600;
+    await program.methods
+      .extendLock(null, new BN(unlockTs))
+      .accounts({ user: user.publicKey, pool: poolA, userStake: original })
+      .rpc();
+
+    await program.methods
+      .splitPosition(new BN(toBase(4).toString()), 7)
+      .accounts({
+        user: user.publicKey,
+        pool: poolA,
+        userStake: original,
+        newStake: splitOff,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    const from = await program.account.userStake.fetch(original);
+    const to = await program.account.userStake.fetch(splitOff);
+    const owedAtSplit = from.rewardsOwedFp.add(to.rewardsOwedFp);
+    expect(to.amountStaked.toString()).to.eq(toBase(4).toString());
+    expect(to.rewardsOwedFp.toString()).to.eq(owedAtSplit.muln(4).divn(10).toString());
+    expect(to.stakeTs.toString()).to.eq(from.stakeTs.toString());
+    expect(to.lockUnlockTs.toString()).to.eq(unlockTs.toString());
+
+    // The split-off is as locked as the position it came from
+    let code = "";
+    try {
+      await program.methods
+        .unstake(new BN(toBase(4).toString()))
+        .accounts({
+          user: user.publicKey,
+          userStake: splitOff,
+          userAta,
+          pool: poolA,
+          ownerStake: ownerStakeOf(poolA, user.publicKey),
+          poolSigner: poolASigner,
+          vaultAta: vaultA,
+          stats,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        })
+        .rpc();
+    } catch (e) {
+      code = e.error?.errorCode?.code;
+    }
+    expect(code).to.eq("Lockup");
+
+    await program.methods
+      .mergePositions()
+      .accounts({ user: user.publicKey, pool: poolA, userStake: original, source: splitOff })
+      .rpc();
+    const merged = await program.account.userStake.fetch(original);
+    expect(merged.amountStaked.toString()).to.eq(toBase(10).toString());
+    expect(merged.rewardsOwedFp.gte(owedAtSplit)).to.eq(true);
+    expect(merged.lockUnlockTs.toString()).to.eq(unlockTs.toString());
+    expect(await provider.connection.getAccountInfo(splitOff)).to.eq(null);
+  });
+
+  it("Q) Only the approved wallet can take a transferred position", async () => {
+    const asAdmin = (m: any) => m.accounts({ admin: user.publicKey, pool: poolA }).rpc();
+    const positionOf = (owner: PublicKey, index: number) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("user_stake"), poolA.toBuffer(), owner.toBuffer(), new BN(index).toArrayLike(Buffer, "le", 4)],
+        program.programId
+      )[0];
+    const fund = async (wallet: Keypair) =>
+      provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(wallet.publicKey, LAMPORTS_PER_SOL)
+      );
+    const newOwner = Keypair.generate();
+    const imposter = Keypair.generate();
+    await fund(newOwner);
+    await fund(imposter);
+
+    const position = positionOf(user.publicKey, 8);
+    await program.methods
+      .stake(new BN(toBase(3).toString()), null, null, 8, null)
+      .accounts({
+        user: user.publicKey,
+        userStake: position,
+        userAta,
+        pool: poolA,
+        ownerStake: ownerStakeOf(poolA, user.publicKey),
+        poolSigner: poolASigner,
+        vaultAta: vaultA,
+        mint,
+        stats,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .rpc();
+    const before = await program.account.userStake.fetch(position);
+    await program.methods
+      .approveTransfer(newOwner.publicKey)
+      .accounts({ user: user.publicKey, pool: poolA, userStake: position })
+      .rpc();
+    const heldBefore = (await program.account.ownerStake.fetch(ownerStakeOf(poolA, user.publicKey))).amountStaked;
+
+    const accept = (signer: Keypair) =>
+      program.methods
+        .acceptTransfer(0)
+        .accounts({
+          newOwner: signer.publicKey,
+          owner: user.publicKey,
+          pool: poolA,
+          userStake: position,
+          ownerStake: ownerStakeOf(poolA, user.publicKey),
+          newStake: positionOf(signer.publicKey, 0),
+          newOwnerStake: ownerStakeOf(poolA, signer.publicKey),
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([signer])
+        .rpc();
+    const codeOf = async (attempt: Promise<unknown>) => {
+      try {
+        await attempt;
+      } catch (e) {
+        return e.error?.errorCode?.code;
+      }
+      return "";
+    };
+
+    expect(await codeOf(accept(imposter))).to.eq("Unauthorized");
+
+    // The receiving wallet's cap applies
+    await asAdmin(program.methods.setMaxStakePerUser(new BN(toBase(1).toString())));
+    expect(await codeOf(accept(newOwner))).to.eq("StakeCapExceeded");
+    await asAdmin(program.methods.setMaxStakePerUser(new BN(0)));
+
+    await accept(newOwner);
+    expect(await provider.connection.getAccountInfo(position)).to.eq(null);
+    const moved = await program.account.userStake.fetch(positionOf(newOwner.publicKey, 0));
+    expect(moved.owner.toBase58()).to.eq(newOwner.publicKey.toBase58());
+    expect(moved.amountStaked.toString()).to.eq(before.amountStaked.toString());
+    expect(moved.stakeTs.toString()).to.eq(before.stakeTs.toString());
+    const received = await program.account.ownerStake.fetch(ownerStakeOf(poolA, newOwner.publicKey));
+    expect(received.amountStaked.toString()).to.eq(toBase(3).toString());
+    const held = await program.account.ownerStake.fetch(ownerStakeOf(poolA, user.publicKey));
+    expect(held.amountStaked.toString()).to.eq(heldBefore.sub(new BN(toBase(3).toString())).toString());
+  });
 });
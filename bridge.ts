@@ -0,0 +1,93 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "bn.js";
+import { Commitment, PublicKey } from "@solana/web3.js";
+import { AbcStaking } from "../target/types/abc_staking";
+
+// Event webhook bridge: tails the program's logs over the RPC websocket, decodes
+// its typed events with the IDL and POSTs each one as JSON to every webhook.
+//
+//   BRIDGE_WEBHOOKS=https://example.com/hook[,https://...] \
+//   BRIDGE_EVENTS=LockExpiringEvent,EarlyExitPenaltyEvent \   # optional filter
+//   yarn ts-node scripts/bridge.ts
+//
+// Delivery is at-most-once per running bridge: events emitted while it is down
+// are not replayed, and a webhook still failing after the retries is skipped.
+
+const RETRIES = 3;
+const RETRY_BASE_MS = 500;
+
+type EventPayload = {
+  event: string;
+  signature: string;
+  slot: number;
+  data: unknown;
+};
+
+// Pubkeys and BNs as strings so u64/u128 values survive JSON
+function toJson(value: unknown): unknown {
+  if (value instanceof PublicKey) return value.toBase58();
+  if (BN.isBN(value)) return value.toString();
+  if (Array.isArray(value)) return value.map(toJson);
+  if (value && typeof value === "object") {
+    return Object.fromEntries(Object.entries(value).map(([k, v]) => [k, toJson(v)]));
+  }
+  return value;
+}
+
+async function post(url: string, payload: EventPayload): Promise<void> {
+  for (let attempt = 1; ; attempt++) {
+    try {
+      const res = await fetch(url, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify(payload),
+      });
+      if (res.ok) return;
+      throw new Error(`HTTP ${res.status}`);
+    } catch (err) {
+      if (attempt >= RETRIES) {
+        console.error(`✗ ${payload.event} ${payload.signature} -> ${url}: ${err.message}`);
+        return;
+      }
+      await new Promise((r) => setTimeout(r, RETRY_BASE_MS * 2 ** (attempt - 1)));
+    }
+  }
+}
+
+async function main() {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.AbcStaking as anchor.Program<AbcStaking>;
+
+  const webhooks = (process.env.BRIDGE_WEBHOOKS ?? "").split(",").filter((u) => u.length > 0);
+  if (webhooks.length === 0) {
+    throw new Error("set BRIDGE_WEBHOOKS to one or more comma-separated URLs");
+  }
+  const only = new Set((process.env.BRIDGE_EVENTS ?? "").split(",").filter((e) => e.length > 0));
+  const commitment = (process.env.BRIDGE_COMMITMENT ?? "confirmed") as Commitment;
+
+  const parser = new anchor.EventParser(program.programId, program.coder);
+  provider.connection.onLogs(
+    program.programId,
+    async (logs, ctx) => {
+      if (logs.err) return; // events of failed transactions were rolled back
+      for (const event of parser.parseLogs(logs.logs)) {
+        if (only.size > 0 && !only.has(event.name)) continue;
+        const payload: EventPayload = {
+          event: event.name,
+          signature: logs.signature,
+          slot: ctx.slot,
+          data: toJson(event.data),
+        };
+        await Promise.all(webhooks.map((url) => post(url, payload)));
+      }
+    },
+    commitment
+  );
+  console.log(`Bridging ${program.programId.toBase58()} events (${commitment}) to ${webhooks.length} webhook(s)`);
+}
+
+main().catch((err) => {
+  console.error(err);
+  process.exit(1);
+});
@@ -0,0 +1,190 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  AddressLookupTableAccount,
+  AddressLookupTableProgram,
+  ComputeBudgetProgram,
+  Connection,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+  TransactionMessage,
+  VersionedTransaction,
+} from "@solana/web3.js";
+import { ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { AbcStaking } from "../target/types/abc_staking";
+
+// Shared transaction assembly for scripts and integrations. Account-heavy
+// instructions (claim with fee/referral/vesting accounts, claim_as routes,
+// set_params_many) can exceed the default 200k CU or the legacy size limit, so
+// callers measure CU by simulation and compile v0 messages against lookup tables.
+
+const MAX_COMPUTE_UNITS = 1_400_000;
+const CU_MARGIN = 1.1; // headroom over the simulated figure
+const ALT_EXTEND_BATCH = 20; // addresses per extend instruction that fit one transaction
+
+export type AssemblyOptions = {
+  lookupTables?: AddressLookupTableAccount[];
+  // Price per CU in micro-lamports; defaults to the recent median for the accounts touched
+  microLamports?: number;
+  // Skip simulation and use this CU limit instead
+  computeUnits?: number;
+};
+
+function compile(
+  payer: PublicKey,
+  blockhash: string,
+  ixs: TransactionInstruction[],
+  lookupTables: AddressLookupTableAccount[]
+): VersionedTransaction {
+  const message = new TransactionMessage({
+    payerKey: payer,
+    recentBlockhash: blockhash,
+    instructions: ixs,
+  }).compileToV0Message(lookupTables);
+  return new VersionedTransaction(message);
+}
+
+// Compute units `ixs` consume, measured by simulating them under the maximum limit
+export async function measureComputeUnits(
+  connection: Connection,
+  payer: PublicKey,
+  ixs: TransactionInstruction[],
+  lookupTables: AddressLookupTableAccount[] = []
+): Promise<number> {
+  const { blockhash } = await connection.getLatestBlockhash();
+  const tx = compile(
+    payer,
+    blockhash,
+    [ComputeBudgetProgram.setComputeUnitLimit({ units: MAX_COMPUTE_UNITS }), ...ixs],
+    lookupTables
+  );
+  const sim = await connection.simulateTransaction(tx, { sigVerify: false, replaceRecentBlockhash: true });
+  if (sim.value.err) {
+    throw new Error(`simulation failed: ${JSON.stringify(sim.value.err)}\n${(sim.value.logs ?? []).join("\n")}`);
+  }
+  return sim.value.unitsConsumed ?? MAX_COMPUTE_UNITS;
+}
+
+// Median recent priority fee (micro-lamports per CU) paid for the writable accounts in `ixs`
+export async function recentPriorityFee(connection: Connection, ixs: TransactionInstruction[]): Promise<number> {
+  const writable = new Map<string, PublicKey>();
+  for (const ix of ixs) {
+    for (const meta of ix.keys) {
+      if (meta.isWritable) writable.set(meta.pubkey.toBase58(), meta.pubkey);
+    }
+  }
+  const fees = (await connection.getRecentPrioritizationFees({ lockedWritableAccounts: [...writable.values()] }))
+    .map((f) => f.prioritizationFee)
+    .sort((a, b) => a - b);
+  return fees.length === 0 ? 0 : fees[Math.floor(fees.length / 2)];
+}
+
+// Prepend ComputeBudget instructions for `units` CU at `microLamports` per CU
+export function withComputeBudget(
+  ixs: TransactionInstruction[],
+  units: number,
+  microLamports: number
+): TransactionInstruction[] {
+  const budget = [ComputeBudgetProgram.setComputeUnitLimit({ units: Math.min(units, MAX_COMPUTE_UNITS) })];
+  if (microLamports > 0) {
+    budget.push(ComputeBudgetProgram.setComputeUnitPrice({ microLamports }));
+  }
+  return [...budget, ...ixs];
+}
+
+// Unsigned v0 transaction for `ixs` with a measured CU limit and priority fee
+export async function buildVersionedTransaction(
+  connection: Connection,
+  payer: PublicKey,
+  ixs: TransactionInstruction[],
+  opts: AssemblyOptions = {}
+): Promise<VersionedTransaction> {
+  const lookupTables = opts.lookupTables ?? [];
+  const units =
+    opts.computeUnits ?? Math.ceil((await measureComputeUnits(connection, payer, ixs, lookupTables)) * CU_MARGIN);
+  const microLamports = opts.microLamports ?? (await recentPriorityFee(connection, ixs));
+  const { blockhash } = await connection.getLatestBlockhash();
+  return compile(payer, blockhash, withComputeBudget(ixs, units, microLamports), lookupTables);
+}
+
+// Fetch lookup tables by address, skipping any that no longer exist
+export async function fetchLookupTables(
+  connection: Connection,
+  addresses: PublicKey[]
+): Promise<AddressLookupTableAccount[]> {
+  const tables = await Promise.all(addresses.map((a) => connection.getAddressLookupTable(a)));
+  return tables.map((t) => t.value).filter((t): t is AddressLookupTableAccount => t !== null);
+}
+
+// Accounts every instruction on `pool` references that never change for its lifetime
+export async function poolStaticAccounts(
+  program: anchor.Program<AbcStaking>,
+  pool: PublicKey
+): Promise<PublicKey[]> {
+  const state = await program.account.pool.fetch(pool);
+  const [poolSigner] = PublicKey.findProgramAddressSync(
+    [Buffer.from("pool"), state.mint.toBuffer(), state.admin.toBuffer(), state.poolId.toArrayLike(Buffer, "le", 8)],
+    program.programId
+  );
+  const [rewardSigner] = PublicKey.findProgramAddressSync([Buffer.from("reward"), pool.toBuffer()], program.programId);
+  const [stats] = PublicKey.findProgramAddressSync([Buffer.from("protocol_stats")], program.programId);
+  const [config] = PublicKey.findProgramAddressSync([Buffer.from("protocol_config")], program.programId);
+
+  const accounts = [
+    pool,
+    poolSigner,
+    state.vault,
+    state.mint,
+    rewardSigner,
+    state.rewardVault,
+    state.reward2Mint,
+    state.reward2Vault,
+    stats,
+    config,
+    TOKEN_PROGRAM_ID,
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+    SystemProgram.programId,
+    program.programId,
+  ];
+  return accounts.filter((a) => !a.equals(PublicKey.default));
+}
+
+// Create the pool's lookup table if it has none (recording it on the pool), then
+// extend it with any static account it is missing. Admin-only; returns the table.
+export async function syncPoolLookupTable(
+  program: anchor.Program<AbcStaking>,
+  pool: PublicKey
+): Promise<PublicKey> {
+  const provider = program.provider as anchor.AnchorProvider;
+  const admin = provider.wallet.publicKey;
+  const wanted = await poolStaticAccounts(program, pool);
+  let table = (await program.account.pool.fetch(pool)).lookupTable;
+
+  let present: PublicKey[] = [];
+  if (table.equals(PublicKey.default)) {
+    const slot = await provider.connection.getSlot("finalized");
+    const [createIx, address] = AddressLookupTableProgram.createLookupTable({
+      authority: admin,
+      payer: admin,
+      recentSlot: slot,
+    });
+    const recordIx = await program.methods.setLookupTable(address).accounts({ admin, pool }).instruction();
+    await provider.sendAndConfirm(new Transaction().add(createIx, recordIx));
+    table = address;
+  } else {
+    present = (await provider.connection.getAddressLookupTable(table)).value?.state.addresses ?? [];
+  }
+
+  const missing = wanted.filter((a) => !present.some((p) => p.equals(a)));
+  for (let i = 0; i < missing.length; i += ALT_EXTEND_BATCH) {
+    const extendIx = AddressLookupTableProgram.extendLookupTable({
+      lookupTable: table,
+      authority: admin,
+      payer: admin,
+      addresses: missing.slice(i, i + ALT_EXTEND_BATCH),
+    });
+    await provider.sendAndConfirm(new Transaction().add(extendIx));
+  }
+  return table;
+}
@@ -0,0 +1,119 @@
+// Canonical reward-math test vectors, computed with the program's own helpers.
+//
+//   cargo run --features test-vectors --bin test_vectors > test_vectors.json
+//   cargo run --features test-vectors --bin test_vectors -- --check test_vectors.json
+//
+// Clients (TS tests, Python tooling) replay the same inputs and must match every
+// value exactly. Q64.64 values are decimal strings since they overflow JSON numbers.
+
+use abc_staking::{accrue_to, accrued_fp, apy_to_compound_rate_fp, apy_to_rate_fp, compound_growth_fp, Pool, FP_ONE};
+use anchor_lang::AnchorDeserialize;
+
+const DAY: u64 = 86_400;
+const TOKEN: u64 = 1_000_000_000; // 9 decimals
+
+// (apy_bps, principal, seconds)
+const LINEAR: &[(u16, u64, u64)] = &[
+    (1_000, 10 * TOKEN, 30 * DAY),
+    (2_000, 10 * TOKEN, 30 * DAY),
+    (500, 1_000 * TOKEN, 365 * DAY),
+    (10_000, TOKEN, 1),
+    (1, 1, 1),
+];
+
+// (emission_per_second, total_weight, weight, seconds)
+const FIXED_EMISSION: &[(u64, u64, u64, u64)] = &[
+    (TOKEN, 100 * TOKEN, 10 * TOKEN, DAY),
+    (1, 3, 1, 7),
+    (50 * TOKEN, 1_000_000 * TOKEN, 1, 365 * DAY),
+];
+
+// (apy_bps, principal, seconds)
+const COMPOUNDING: &[(u16, u64, u64)] = &[
+    (500, 1_000 * TOKEN, 365 * DAY),
+    (1_000, 1_000 * TOKEN, 365 * DAY),
+    (10_000, 1_000 * TOKEN, 365 * DAY),
+    (1_000, 10 * TOKEN, 30 * DAY),
+];
+
+// A pool with every field zeroed (no bonus, cap, halving or epochs) holding `total_staked`
+fn blank_pool(total_staked: u64) -> Pool {
+    let zeros = vec![0u8; Pool::SIZE];
+    let mut pool = Pool::deserialize(&mut zeros.as_slice()).expect("pool layout");
+    pool.total_staked = total_staked;
+    pool
+}
+
+fn vectors() -> String {
+    let mut out = String::from("{\n  \"linear\": [\n");
+    let rows: Vec<String> = LINEAR
+        .iter()
+        .map(|&(apy_bps, principal, seconds)| {
+            let rate_fp = apy_to_rate_fp(apy_bps);
+            let mut pool = blank_pool(principal);
+            pool.reward_rate_fp = rate_fp;
+            let acc_fp = accrue_to(&mut pool, seconds as i64).expect("accrual");
+            let owed_fp = accrued_fp(principal as u128, 0, acc_fp).expect("owed");
+            format!(
+                "    {{\"apy_bps\": {}, \"principal\": {}, \"seconds\": {}, \"rate_fp\": \"{}\", \"acc_fp\": \"{}\", \"owed_fp\": \"{}\", \"tokens\": {}}}",
+                apy_bps, principal, seconds, rate_fp, acc_fp, owed_fp, owed_fp / FP_ONE
+            )
+        })
+        .collect();
+    out += &rows.join(",\n");
+
+    out += "\n  ],\n  \"fixed_emission\": [\n";
+    let rows: Vec<String> = FIXED_EMISSION
+        .iter()
+        .map(|&(eps, total_weight, weight, seconds)| {
+            let mut pool = blank_pool(total_weight);
+            pool.fixed_emission = true;
+            pool.reward_rate_fp = eps as u128 * FP_ONE;
+            let acc_fp = accrue_to(&mut pool, seconds as i64).expect("accrual");
+            let owed_fp = accrued_fp(weight as u128, 0, acc_fp).expect("owed");
+            format!(
+                "    {{\"emission_per_second\": {}, \"total_weight\": {}, \"weight\": {}, \"seconds\": {}, \"acc_fp\": \"{}\", \"owed_fp\": \"{}\", \"tokens\": {}}}",
+                eps, total_weight, weight, seconds, acc_fp, owed_fp, owed_fp / FP_ONE
+            )
+        })
+        .collect();
+    out += &rows.join(",\n");
+
+    out += "\n  ],\n  \"compounding\": [\n";
+    let rows: Vec<String> = COMPOUNDING
+        .iter()
+        .map(|&(apy_bps, principal, seconds)| {
+            let rate_fp = apy_to_compound_rate_fp(apy_bps).expect("rate");
+            let mut pool = blank_pool(principal);
+            pool.compounding = true;
+            pool.compound_rate_fp = rate_fp;
+            pool.compound_index_fp = FP_ONE;
+            accrue_to(&mut pool, seconds as i64).expect("accrual");
+            let index_fp = pool.compound_index_fp;
+            let owed_fp = compound_growth_fp(principal as u128 * FP_ONE, FP_ONE, index_fp).expect("growth");
+            format!(
+                "    {{\"apy_bps\": {}, \"principal\": {}, \"seconds\": {}, \"rate_fp\": \"{}\", \"index_fp\": \"{}\", \"owed_fp\": \"{}\", \"tokens\": {}}}",
+                apy_bps, principal, seconds, rate_fp, index_fp, owed_fp, owed_fp / FP_ONE
+            )
+        })
+        .collect();
+    out += &rows.join(",\n");
+    out += "\n  ]\n}\n";
+    out
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let generated = vectors();
+    match args.get(1).map(String::as_str) {
+        Some("--check") => {
+            let path = args.get(2).expect("usage: test_vectors --check <file>");
+            let on_disk = std::fs::read_to_string(path).expect("read vectors");
+            if on_disk != generated {
+                eprintln!("{} is stale; regenerate it with the command at the top of test_vectors.rs", path);
+                std::process::exit(1);
+            }
+        }
+        _ => print!("{}", generated),
+    }
+}